@@ -0,0 +1,12 @@
+//! Error types returned by this crate.
+
+use thiserror::Error;
+
+/// Errors that can occur while authenticating with or calling the Schwab API.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A [`ChannelMessenger`](crate::token::channel_messenger::ChannelMessenger)
+    /// failed to deliver the auth URL or retrieve the authorization code.
+    #[error("channel messenger error: {0}")]
+    ChannelMessenger(String),
+}
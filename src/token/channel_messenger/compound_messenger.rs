@@ -1,68 +1,133 @@
-//! A messenger that uses standard input/output.
+//! A messenger that chains several messengers together, falling over to the
+//! next one whenever the current one fails or times out.
 
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use tokio::time::timeout;
 
 use super::{AuthContext, ChannelMessenger};
 use crate::error::Error;
 
+/// How long a single messenger gets to complete one attempt before it is
+/// considered failed and the next messenger in the chain is tried.
+const DEFAULT_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A chain of [`ChannelMessenger`]s tried in order. When every messenger in
+/// the chain has failed, the chain optionally wraps back around to the start
+/// for up to `max_retries` additional passes before giving up.
+///
+/// The index of whichever messenger last succeeded is remembered in
+/// `selected`, so `receive_auth_message` starts from the messenger that
+/// actually delivered the auth message instead of racing `send`'s choice
+/// against a fresh walk from index 0.
 #[derive(Debug)]
-pub struct CompoundMessenger<CM0: ChannelMessenger, CM1: ChannelMessenger> {
-    select: AtomicUsize,
-    default: CM0,
-    other: CM1,
+pub struct CompoundMessenger {
+    messengers: Vec<Box<dyn ChannelMessenger>>,
+    attempt_timeout: Duration,
+    max_retries: usize,
+    selected: AtomicUsize,
 }
 
-impl<CM0: ChannelMessenger, CM1: ChannelMessenger> CompoundMessenger<CM0, CM1> {
-    pub fn new(default: CM0, other: CM1) -> Self {
+impl CompoundMessenger {
+    /// Build a compound messenger from two or more messengers, tried in the
+    /// order given.
+    pub fn new(messengers: Vec<Box<dyn ChannelMessenger>>) -> Self {
         Self {
-            select: AtomicUsize::new(0),
-            default,
-            other,
+            messengers,
+            attempt_timeout: DEFAULT_ATTEMPT_TIMEOUT,
+            max_retries: 0,
+            selected: AtomicUsize::new(0),
+        }
+    }
+
+    /// Override the per-attempt timeout applied to every messenger in the
+    /// chain. Long-poll messengers (e.g.
+    /// [`WebhookMessenger`](super::WebhookMessenger)) need this raised to at
+    /// least their own poll timeout, or this timeout will cut them off
+    /// before a human can complete the flow.
+    pub fn with_attempt_timeout(mut self, attempt_timeout: Duration) -> Self {
+        self.attempt_timeout = attempt_timeout;
+        self
+    }
+
+    /// Allow the chain to wrap back to the first messenger and retry the
+    /// whole list this many additional times before returning an error.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Try `call` against every messenger in the chain, starting at `start`
+    /// and wrapping around, honoring `attempt_timeout` and `max_retries`.
+    /// Returns the winning messenger's index along with its result.
+    async fn attempt<T>(
+        &self,
+        start: usize,
+        mut call: impl FnMut(&dyn ChannelMessenger) -> BoxFuture<'_, Result<T, Error>>,
+    ) -> Result<(usize, T), Error> {
+        let len = self.messengers.len();
+        if len == 0 {
+            return Err(Error::ChannelMessenger(
+                "no messengers configured".to_string(),
+            ));
+        }
+
+        let mut last_error = Error::ChannelMessenger("no messengers configured".to_string());
+
+        for _ in 0..=self.max_retries {
+            for offset in 0..len {
+                let index = (start + offset) % len;
+                let messenger = &self.messengers[index];
+
+                match timeout(self.attempt_timeout, call(messenger.as_ref())).await {
+                    Ok(Ok(value)) => return Ok((index, value)),
+                    Ok(Err(e)) => {
+                        println!("error:{e}, select next messenger");
+                        last_error = e;
+                    }
+                    Err(_) => {
+                        last_error =
+                            Error::ChannelMessenger("messenger attempt timed out".to_string());
+                        println!("{last_error}, select next messenger");
+                    }
+                }
+            }
         }
+
+        Err(last_error)
     }
 }
 
-impl<CM0: ChannelMessenger, CM1: ChannelMessenger> ChannelMessenger
-    for CompoundMessenger<CM0, CM1>
-{
+#[async_trait]
+impl ChannelMessenger for CompoundMessenger {
     async fn with_context(&mut self, context: AuthContext) -> Result<(), Error> {
-        self.default.with_context(context.clone()).await?;
-        self.other.with_context(context).await?;
+        for messenger in &mut self.messengers {
+            messenger.with_context(context.clone()).await?;
+        }
 
         Ok(())
     }
 
     async fn send_auth_message(&self) -> Result<(), Error> {
-        loop {
-            let result = match self.select.load(Ordering::Acquire) {
-                0 => self.default.send_auth_message().await,
-                1 => self.other.send_auth_message().await,
-                _ => {
-                    return Err(Error::ChannelMessenger(
-                        "No Messengers available to send".to_string(),
-                    ))
-                }
-            };
-
-            match result {
-                Ok(()) => return Ok(()),
-                Err(e) => {
-                    println!("error:{e}, select next messenger");
-                    self.select.fetch_add(1, Ordering::AcqRel);
-                    continue;
-                }
-            }
-        }
+        let (index, ()) = self
+            .attempt(0, |m| Box::pin(m.send_auth_message()))
+            .await?;
+        self.selected.store(index, Ordering::Release);
+
+        Ok(())
     }
 
     async fn receive_auth_message(&self) -> Result<String, Error> {
-        match self.select.load(Ordering::Acquire) {
-            0 => self.default.receive_auth_message().await,
-            1 => self.other.receive_auth_message().await,
-            _ => Err(Error::ChannelMessenger(
-                "No Messengers receive successfully".to_string(),
-            )),
-        }
+        let start = self.selected.load(Ordering::Acquire);
+        let (index, code) = self
+            .attempt(start, |m| Box::pin(m.receive_auth_message()))
+            .await?;
+        self.selected.store(index, Ordering::Release);
+
+        Ok(code)
     }
 }
 
@@ -91,10 +156,10 @@ mod tests {
         };
 
         let certs_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/certs");
-        let mut messenger = CompoundMessenger::new(
-            LocalServerMessenger::new(&certs_dir).await,
-            StdioMessenger::new(),
-        );
+        let mut messenger = CompoundMessenger::new(vec![
+            Box::new(LocalServerMessenger::new(&certs_dir).await),
+            Box::new(StdioMessenger::new()),
+        ]);
 
         messenger.with_context(context).await.unwrap();
         messenger.send_auth_message().await.unwrap();
@@ -0,0 +1,326 @@
+//! A messenger that briefly stands up a local HTTPS server to catch the
+//! OAuth redirect.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls_pemfile::{certs, pkcs8_private_keys};
+use url::Url;
+
+use super::{AuthContext, ChannelMessenger};
+use crate::error::Error;
+
+/// Where `LocalServerMessenger` should source its TLS certificate and key
+/// from before opening the catch-all redirect listener.
+#[derive(Clone)]
+enum CertSource {
+    /// Load `cert.pem`/`key.pem` from a directory on disk.
+    Dir(PathBuf),
+    /// Generate a self-signed `127.0.0.1`/`localhost` certificate in memory,
+    /// optionally caching the PEM pair under a directory for reuse.
+    SelfSigned { cache_dir: Option<PathBuf> },
+}
+
+/// Opens `https://127.0.0.1:<port>` just long enough to catch the OAuth
+/// redirect, then shuts back down.
+///
+/// Caveat for `CompoundMessenger` callers: `receive_auth_message` blocks on
+/// `TcpListener::accept()` in a `spawn_blocking` task, which tokio cannot
+/// cancel. If the outer attempt timeout elapses first, that thread and its
+/// bound listener on the redirect port are left running - a
+/// `with_max_retries` pass that wraps back around to this messenger can
+/// then fail to bind the same address ("address in use") until the
+/// abandoned listener eventually accepts a connection, if ever.
+pub struct LocalServerMessenger {
+    certs: CertSource,
+    context: Mutex<AuthContext>,
+}
+
+impl std::fmt::Debug for LocalServerMessenger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalServerMessenger").finish_non_exhaustive()
+    }
+}
+
+impl LocalServerMessenger {
+    /// Use the TLS certificate and key in `certs_dir` (expected to contain
+    /// `cert.pem` and `key.pem`). The files are read each time
+    /// `receive_auth_message` runs, not here, so a missing or invalid pair
+    /// is reported by `receive_auth_message`.
+    pub async fn new(certs_dir: &Path) -> Self {
+        Self {
+            certs: CertSource::Dir(certs_dir.to_path_buf()),
+            context: Mutex::new(AuthContext::default()),
+        }
+    }
+
+    /// Synthesize a self-signed `127.0.0.1`/`localhost` certificate at
+    /// runtime instead of requiring a pre-placed `cert.pem`/`key.pem`.
+    pub fn new_self_signed() -> Self {
+        Self {
+            certs: CertSource::SelfSigned { cache_dir: None },
+            context: Mutex::new(AuthContext::default()),
+        }
+    }
+
+    /// Like [`Self::new_self_signed`], but cache the generated PEM pair
+    /// under `cache_dir` so repeated runs reuse the same certificate
+    /// instead of generating (and re-trusting) a new one every time.
+    pub fn new_with_generated_certs(cache_dir: &Path) -> Self {
+        Self {
+            certs: CertSource::SelfSigned {
+                cache_dir: Some(cache_dir.to_path_buf()),
+            },
+            context: Mutex::new(AuthContext::default()),
+        }
+    }
+
+    fn server_config(&self) -> Result<ServerConfig, Error> {
+        let (cert_chain, key) = match &self.certs {
+            CertSource::Dir(dir) => load_certs_from_dir(dir)?,
+            CertSource::SelfSigned { cache_dir } => self_signed_certs(cache_dir.as_deref())?,
+        };
+
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(cert_chain, key)
+            .map_err(|e| Error::ChannelMessenger(format!("invalid TLS certificate: {e}")))
+    }
+}
+
+#[async_trait]
+impl ChannelMessenger for LocalServerMessenger {
+    async fn with_context(&mut self, context: AuthContext) -> Result<(), Error> {
+        *self.context.lock().unwrap() = context;
+        Ok(())
+    }
+
+    async fn send_auth_message(&self) -> Result<(), Error> {
+        let context = self.context.lock().unwrap().clone();
+        let auth_url = context
+            .auth_url
+            .ok_or_else(|| Error::ChannelMessenger("no auth_url in context".to_string()))?;
+
+        webbrowser::open(auth_url.as_str())
+            .map_err(|e| Error::ChannelMessenger(format!("failed to open browser: {e}")))
+    }
+
+    async fn receive_auth_message(&self) -> Result<String, Error> {
+        let context = self.context.lock().unwrap().clone();
+        let redirect_url = context
+            .redirect_url
+            .clone()
+            .ok_or_else(|| Error::ChannelMessenger("no redirect_url in context".to_string()))?;
+
+        let config = self.server_config()?;
+
+        let full_redirect_url =
+            tokio::task::spawn_blocking(move || accept_redirect(&redirect_url, config))
+                .await
+                .map_err(|e| Error::ChannelMessenger(format!("server task panicked: {e}")))??;
+
+        self.extract_code(&context, &full_redirect_url)
+    }
+}
+
+fn load_certs_from_dir(certs_dir: &Path) -> Result<(Vec<Certificate>, PrivateKey), Error> {
+    let cert_pem = std::fs::read(certs_dir.join("cert.pem"))
+        .map_err(|e| Error::ChannelMessenger(format!("failed to read cert.pem: {e}")))?;
+    let key_pem = std::fs::read(certs_dir.join("key.pem"))
+        .map_err(|e| Error::ChannelMessenger(format!("failed to read key.pem: {e}")))?;
+
+    parse_cert_and_key(&cert_pem, &key_pem)
+}
+
+fn parse_cert_and_key(
+    cert_pem: &[u8],
+    key_pem: &[u8],
+) -> Result<(Vec<Certificate>, PrivateKey), Error> {
+    let cert_chain = certs(&mut std::io::Cursor::new(cert_pem))
+        .map_err(|e| Error::ChannelMessenger(format!("invalid cert.pem: {e}")))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+
+    let key = pkcs8_private_keys(&mut std::io::Cursor::new(key_pem))
+        .map_err(|e| Error::ChannelMessenger(format!("invalid key.pem: {e}")))?
+        .into_iter()
+        .next()
+        .map(PrivateKey)
+        .ok_or_else(|| Error::ChannelMessenger("key.pem contained no private key".to_string()))?;
+
+    Ok((cert_chain, key))
+}
+
+/// Generate (or, if present, load) a self-signed `127.0.0.1`/`localhost`
+/// certificate pair. When `cache_dir` is given, a previously generated pair
+/// found there is reused instead of minting a new one.
+fn self_signed_certs(cache_dir: Option<&Path>) -> Result<(Vec<Certificate>, PrivateKey), Error> {
+    if let Some(cache_dir) = cache_dir {
+        let cert_path = cache_dir.join("cert.pem");
+        let key_path = cache_dir.join("key.pem");
+        if cert_path.exists() && key_path.exists() {
+            let cert_pem = std::fs::read(&cert_path)
+                .map_err(|e| Error::ChannelMessenger(format!("failed to read cert.pem: {e}")))?;
+            let key_pem = std::fs::read(&key_path)
+                .map_err(|e| Error::ChannelMessenger(format!("failed to read key.pem: {e}")))?;
+            return parse_cert_and_key(&cert_pem, &key_pem);
+        }
+    }
+
+    let cert = rcgen::generate_simple_self_signed(vec![
+        "127.0.0.1".to_string(),
+        "localhost".to_string(),
+    ])
+    .map_err(|e| Error::ChannelMessenger(format!("failed to generate self-signed cert: {e}")))?;
+
+    let cert_pem = cert
+        .serialize_pem()
+        .map_err(|e| Error::ChannelMessenger(format!("failed to serialize cert: {e}")))?;
+    let key_pem = cert.serialize_private_key_pem();
+
+    if let Some(cache_dir) = cache_dir {
+        std::fs::create_dir_all(cache_dir)
+            .map_err(|e| Error::ChannelMessenger(format!("failed to create cache dir: {e}")))?;
+        std::fs::write(cache_dir.join("cert.pem"), &cert_pem)
+            .map_err(|e| Error::ChannelMessenger(format!("failed to cache cert.pem: {e}")))?;
+
+        let key_path = cache_dir.join("key.pem");
+        write_key_restricted(&key_path, &key_pem)?;
+    }
+
+    parse_cert_and_key(cert_pem.as_bytes(), key_pem.as_bytes())
+}
+
+/// Write a cached private key restricted to owner read/write from the moment
+/// the file is created, rather than writing it world-readable under the
+/// default umask and narrowing permissions afterward.
+fn write_key_restricted(path: &Path, key_pem: &str) -> Result<(), Error> {
+    #[cfg(unix)]
+    {
+        use std::fs::OpenOptions;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| file.write_all(key_pem.as_bytes()))
+            .map_err(|e| Error::ChannelMessenger(format!("failed to cache key.pem: {e}")))
+    }
+
+    #[cfg(not(unix))]
+    {
+        std::fs::write(path, key_pem)
+            .map_err(|e| Error::ChannelMessenger(format!("failed to cache key.pem: {e}")))
+    }
+}
+
+/// Accept a single TLS connection on `redirect_url`'s host/port and return
+/// the full URL the client actually landed on, query string included.
+fn accept_redirect(redirect_url: &Url, config: ServerConfig) -> Result<Url, Error> {
+    let addr = format!(
+        "{}:{}",
+        redirect_url.host_str().unwrap_or("127.0.0.1"),
+        redirect_url.port_or_known_default().unwrap_or(443)
+    );
+
+    let listener = TcpListener::bind(&addr)
+        .map_err(|e| Error::ChannelMessenger(format!("failed to bind {addr}: {e}")))?;
+    let (mut stream, _) = listener
+        .accept()
+        .map_err(|e| Error::ChannelMessenger(format!("failed to accept connection: {e}")))?;
+
+    let mut conn = rustls::ServerConnection::new(Arc::new(config))
+        .map_err(|e| Error::ChannelMessenger(format!("TLS handshake setup failed: {e}")))?;
+    let mut tls_stream = rustls::Stream::new(&mut conn, &mut stream);
+
+    let mut request_line = String::new();
+    {
+        // Scoped so this borrow of `tls_stream` ends before we write the
+        // response and drain through it directly below.
+        let mut reader = BufReader::new(&mut tls_stream);
+        reader
+            .read_line(&mut request_line)
+            .map_err(|e| Error::ChannelMessenger(format!("failed to read request: {e}")))?;
+    }
+
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| Error::ChannelMessenger("malformed HTTP request line".to_string()))?;
+
+    let full_url = redirect_url
+        .join(path)
+        .map_err(|e| Error::ChannelMessenger(format!("invalid redirect path: {e}")))?;
+
+    let _ = write!(
+        tls_stream,
+        "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    );
+
+    // Drain any remaining request bytes so the client's TLS close_notify completes cleanly.
+    let mut discard = [0u8; 1024];
+    let _ = tls_stream.read(&mut discard);
+
+    Ok(full_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_signed_certs_round_trip_without_cache() {
+        let (cert_chain, _key) = self_signed_certs(None).unwrap();
+        assert_eq!(1, cert_chain.len());
+    }
+
+    #[test]
+    fn self_signed_certs_are_cached_and_reused() {
+        let cache_dir = std::env::temp_dir().join(format!(
+            "schwab_api-test-certs-{}-{}",
+            std::process::id(),
+            "reused"
+        ));
+        let _ = std::fs::remove_dir_all(&cache_dir);
+
+        let (first_chain, _first_key) = self_signed_certs(Some(&cache_dir)).unwrap();
+        let (second_chain, _second_key) = self_signed_certs(Some(&cache_dir)).unwrap();
+
+        assert_eq!(first_chain[0].0, second_chain[0].0);
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cached_key_is_restricted_to_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let cache_dir = std::env::temp_dir().join(format!(
+            "schwab_api-test-certs-{}-{}",
+            std::process::id(),
+            "perms"
+        ));
+        let _ = std::fs::remove_dir_all(&cache_dir);
+
+        self_signed_certs(Some(&cache_dir)).unwrap();
+
+        let mode = std::fs::metadata(cache_dir.join("key.pem"))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o777;
+        assert_eq!(0o600, mode);
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+}
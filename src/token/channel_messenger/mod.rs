@@ -0,0 +1,159 @@
+//! Channel messengers negotiate delivery of the OAuth authorization URL to
+//! the user and retrieval of the resulting authorization code.
+
+mod compound_messenger;
+mod local_server;
+mod socket_messenger;
+mod stdio_messenger;
+mod webhook_messenger;
+
+pub use compound_messenger::CompoundMessenger;
+pub use local_server::LocalServerMessenger;
+pub use socket_messenger::SocketMessenger;
+pub use stdio_messenger::StdioMessenger;
+pub use webhook_messenger::WebhookMessenger;
+
+use async_trait::async_trait;
+use oauth2::CsrfToken;
+use subtle::ConstantTimeEq;
+use url::Url;
+
+use crate::error::Error;
+
+/// The shared state a messenger needs to send the auth URL and validate the
+/// code that comes back.
+#[derive(Debug, Clone, Default)]
+pub struct AuthContext {
+    pub auth_url: Option<Url>,
+    pub csrf: Option<CsrfToken>,
+    pub redirect_url: Option<Url>,
+}
+
+/// A transport capable of delivering the Schwab OAuth authorization URL to
+/// the user and retrieving the authorization code from the redirect.
+#[async_trait]
+pub trait ChannelMessenger: std::fmt::Debug + Send + Sync {
+    /// Store the context this messenger needs to send/receive the auth message.
+    async fn with_context(&mut self, context: AuthContext) -> Result<(), Error>;
+
+    /// Deliver the authorization URL to the user.
+    async fn send_auth_message(&self) -> Result<(), Error>;
+
+    /// Wait for the user to complete authorization and return the code.
+    async fn receive_auth_message(&self) -> Result<String, Error>;
+
+    /// Validate `redirect_url`'s `state` query parameter against
+    /// `context.csrf` before pulling the authorization `code` out of it.
+    ///
+    /// Implementors should route the redirect URL through this before
+    /// surfacing a `code` from `receive_auth_message`, so a forged or missing
+    /// `state` is rejected as a CSRF attempt rather than silently accepted.
+    fn extract_code(&self, context: &AuthContext, redirect_url: &Url) -> Result<String, Error> {
+        let expected = context
+            .csrf
+            .as_ref()
+            .ok_or_else(|| Error::ChannelMessenger("CSRF state mismatch".to_string()))?
+            .secret()
+            .as_bytes();
+
+        let actual = redirect_url
+            .query_pairs()
+            .find(|(key, _)| key == "state")
+            .map(|(_, value)| value.into_owned());
+
+        let state_matches = match &actual {
+            Some(actual) if actual.len() == expected.len() => {
+                expected.ct_eq(actual.as_bytes()).into()
+            }
+            _ => false,
+        };
+
+        if !state_matches {
+            return Err(Error::ChannelMessenger("CSRF state mismatch".to_string()));
+        }
+
+        redirect_url
+            .query_pairs()
+            .find(|(key, _)| key == "code")
+            .map(|(_, value)| value.into_owned())
+            .ok_or_else(|| Error::ChannelMessenger("no code in redirect url".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct NullMessenger;
+
+    #[async_trait]
+    impl ChannelMessenger for NullMessenger {
+        async fn with_context(&mut self, _context: AuthContext) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn send_auth_message(&self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn receive_auth_message(&self) -> Result<String, Error> {
+            Ok(String::new())
+        }
+    }
+
+    fn context_with_csrf(csrf: &str) -> AuthContext {
+        AuthContext {
+            auth_url: None,
+            csrf: Some(CsrfToken::new(csrf.to_string())),
+            redirect_url: None,
+        }
+    }
+
+    #[test]
+    fn extract_code_accepts_matching_state() {
+        let redirect_url: Url = "https://127.0.0.1:8081/?state=CSRF&code=code".parse().unwrap();
+
+        assert_eq!(
+            "code",
+            NullMessenger
+                .extract_code(&context_with_csrf("CSRF"), &redirect_url)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn extract_code_rejects_mismatched_state() {
+        let redirect_url: Url = "https://127.0.0.1:8081/?state=FORGED&code=code"
+            .parse()
+            .unwrap();
+
+        let err = NullMessenger
+            .extract_code(&context_with_csrf("CSRF"), &redirect_url)
+            .unwrap_err();
+
+        assert!(matches!(err, Error::ChannelMessenger(msg) if msg == "CSRF state mismatch"));
+    }
+
+    #[test]
+    fn extract_code_rejects_missing_state() {
+        let redirect_url: Url = "https://127.0.0.1:8081/?code=code".parse().unwrap();
+
+        let err = NullMessenger
+            .extract_code(&context_with_csrf("CSRF"), &redirect_url)
+            .unwrap_err();
+
+        assert!(matches!(err, Error::ChannelMessenger(msg) if msg == "CSRF state mismatch"));
+    }
+
+    #[test]
+    fn extract_code_rejects_missing_csrf_in_context() {
+        let redirect_url: Url = "https://127.0.0.1:8081/?state=CSRF&code=code".parse().unwrap();
+
+        let err = NullMessenger
+            .extract_code(&AuthContext::default(), &redirect_url)
+            .unwrap_err();
+
+        assert!(matches!(err, Error::ChannelMessenger(msg) if msg == "CSRF state mismatch"));
+    }
+}
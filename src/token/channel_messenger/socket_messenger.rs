@@ -0,0 +1,175 @@
+//! A messenger that hands the interactive auth step off to a companion
+//! process over a local IPC channel, rather than sharing stdio or a browser.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use url::Url;
+
+#[cfg(unix)]
+use tokio::net::UnixStream;
+#[cfg(windows)]
+use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeClient};
+
+use super::{AuthContext, ChannelMessenger};
+use crate::error::Error;
+
+#[cfg(unix)]
+type Stream = UnixStream;
+#[cfg(windows)]
+type Stream = NamedPipeClient;
+
+/// Sends the auth URL down a Unix domain socket (or, on Windows, a named
+/// pipe) and reads the resulting redirect URL back over that *same*
+/// connection, one line each way. A companion process on the other end - a
+/// desktop helper, a browser shim, a supervising daemon - owns the
+/// interactive part, and is expected to hold its end of the connection open
+/// between the write and the read.
+#[derive(Debug, Clone)]
+pub struct SocketMessenger {
+    path: PathBuf,
+    context: Arc<Mutex<AuthContext>>,
+    stream: Arc<Mutex<Option<Stream>>>,
+}
+
+impl SocketMessenger {
+    /// `path` is the Unix domain socket path on Unix, or the named pipe path
+    /// (e.g. `\\.\pipe\schwab-auth`) on Windows.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            context: Arc::new(Mutex::new(AuthContext::default())),
+            stream: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    #[cfg(unix)]
+    async fn connect(&self) -> Result<Stream, Error> {
+        UnixStream::connect(&self.path).await.map_err(|e| {
+            Error::ChannelMessenger(format!("failed to connect to {}: {e}", self.path.display()))
+        })
+    }
+
+    #[cfg(windows)]
+    async fn connect(&self) -> Result<Stream, Error> {
+        let path = self
+            .path
+            .to_str()
+            .ok_or_else(|| Error::ChannelMessenger("invalid named pipe path".to_string()))?;
+
+        ClientOptions::new()
+            .open(path)
+            .map_err(|e| Error::ChannelMessenger(format!("failed to connect to {path}: {e}")))
+    }
+}
+
+#[async_trait]
+impl ChannelMessenger for SocketMessenger {
+    async fn with_context(&mut self, context: AuthContext) -> Result<(), Error> {
+        *self.context.lock().unwrap() = context;
+        Ok(())
+    }
+
+    async fn send_auth_message(&self) -> Result<(), Error> {
+        let auth_url = self
+            .context
+            .lock()
+            .unwrap()
+            .auth_url
+            .clone()
+            .ok_or_else(|| Error::ChannelMessenger("no auth_url in context".to_string()))?;
+
+        let mut stream = self.connect().await?;
+        stream
+            .write_all(format!("{auth_url}\n").as_bytes())
+            .await
+            .map_err(|e| Error::ChannelMessenger(format!("failed to write to socket: {e}")))?;
+
+        *self.stream.lock().unwrap() = Some(stream);
+
+        Ok(())
+    }
+
+    async fn receive_auth_message(&self) -> Result<String, Error> {
+        let context = self.context.lock().unwrap().clone();
+
+        let stream = self.stream.lock().unwrap().take().ok_or_else(|| {
+            Error::ChannelMessenger(
+                "send_auth_message must succeed before receive_auth_message".to_string(),
+            )
+        })?;
+
+        let mut line = String::new();
+        BufReader::new(stream)
+            .read_line(&mut line)
+            .await
+            .map_err(|e| Error::ChannelMessenger(format!("failed to read from socket: {e}")))?;
+
+        let redirect_url: Url = line
+            .trim()
+            .parse()
+            .map_err(|e| Error::ChannelMessenger(format!("invalid redirect url: {e}")))?;
+
+        self.extract_code(&context, &redirect_url)
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use super::*;
+
+    use oauth2::CsrfToken;
+    use tokio::net::UnixListener;
+
+    #[tokio::test]
+    async fn test_send_then_receive_over_same_connection() {
+        let socket_path = std::env::temp_dir().join(format!(
+            "schwab_api-test-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        let companion = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut sent = String::new();
+            BufReader::new(&mut stream)
+                .read_line(&mut sent)
+                .await
+                .unwrap();
+            assert_eq!(sent.trim(), "https://127.0.0.1:8081/");
+
+            stream
+                .write_all(b"https://127.0.0.1:8081/?state=CSRF&code=code\n")
+                .await
+                .unwrap();
+        });
+
+        let mut messenger = SocketMessenger::new(&socket_path);
+        messenger
+            .with_context(AuthContext {
+                auth_url: Some("https://127.0.0.1:8081/".parse().unwrap()),
+                csrf: Some(CsrfToken::new("CSRF".to_string())),
+                redirect_url: None,
+            })
+            .await
+            .unwrap();
+
+        messenger.send_auth_message().await.unwrap();
+        assert_eq!("code", messenger.receive_auth_message().await.unwrap());
+
+        companion.await.unwrap();
+        let _ = std::fs::remove_file(&socket_path);
+    }
+
+    #[tokio::test]
+    async fn test_receive_without_send_is_rejected() {
+        let messenger = SocketMessenger::new("/tmp/schwab_api-test-unused.sock");
+        let err = messenger.receive_auth_message().await.unwrap_err();
+        assert!(matches!(err, Error::ChannelMessenger(_)));
+    }
+}
@@ -0,0 +1,61 @@
+//! A messenger that uses standard input/output.
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use url::Url;
+
+use super::{AuthContext, ChannelMessenger};
+use crate::error::Error;
+
+/// Prints the auth URL to stdout and reads the resulting redirect URL back
+/// from stdin. Useful for headful, interactive sessions on a local machine.
+#[derive(Debug, Default)]
+pub struct StdioMessenger {
+    context: AuthContext,
+}
+
+impl StdioMessenger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ChannelMessenger for StdioMessenger {
+    async fn with_context(&mut self, context: AuthContext) -> Result<(), Error> {
+        self.context = context;
+        Ok(())
+    }
+
+    async fn send_auth_message(&self) -> Result<(), Error> {
+        let auth_url = self
+            .context
+            .auth_url
+            .as_ref()
+            .ok_or_else(|| Error::ChannelMessenger("no auth_url in context".to_string()))?;
+
+        println!("Navigate to the following URL to authenticate: {auth_url}");
+        println!("Paste the redirect URL you land on here:");
+
+        Ok(())
+    }
+
+    async fn receive_auth_message(&self) -> Result<String, Error> {
+        // `tokio::io::stdin()` (not `std::io::stdin()`) gives this an actual
+        // `.await` point, so `CompoundMessenger`'s outer `timeout()` can
+        // preempt it and move on to the next messenger instead of blocking
+        // the whole task forever on a line nobody is going to type.
+        let mut line = String::new();
+        BufReader::new(tokio::io::stdin())
+            .read_line(&mut line)
+            .await
+            .map_err(|e| Error::ChannelMessenger(e.to_string()))?;
+
+        let redirect_url: Url = line
+            .trim()
+            .parse()
+            .map_err(|e| Error::ChannelMessenger(format!("invalid redirect url: {e}")))?;
+
+        self.extract_code(&self.context, &redirect_url)
+    }
+}
@@ -0,0 +1,281 @@
+//! A messenger that delegates to a remote HTTP callback, for headless
+//! deployments with no local stdio or browser to hand the user.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::time::sleep;
+use url::Url;
+
+use super::{AuthContext, ChannelMessenger};
+use crate::error::Error;
+
+/// How long to wait between polls of `status_url` while waiting for the
+/// redirect to show up.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long to keep polling `status_url` before giving up.
+const DEFAULT_POLL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Serialize)]
+struct SendAuthPayload<'a> {
+    auth_url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<&'a str>,
+}
+
+#[derive(serde::Deserialize)]
+struct PollResponse {
+    redirect_url: Option<String>,
+}
+
+/// POSTs the auth URL to a remote `callback_url` and long-polls `status_url`
+/// for the resulting redirect, so a headless process can hand the
+/// interactive step off to some other system.
+///
+/// `receive_auth_message` can legitimately block for up to `poll_timeout`
+/// (10 minutes by default) waiting on a human. When this messenger sits in
+/// a [`CompoundMessenger`](super::CompoundMessenger), make sure its
+/// `with_attempt_timeout` is raised to at least [`Self::poll_timeout`] -
+/// `CompoundMessenger`'s default 30s attempt timeout would otherwise cut the
+/// long-poll off long before anyone could complete the flow.
+pub struct WebhookMessenger {
+    callback_url: Url,
+    status_url: Url,
+    bearer_token: Option<String>,
+    poll_interval: Duration,
+    poll_timeout: Duration,
+    client: reqwest::Client,
+    context: Mutex<AuthContext>,
+}
+
+impl std::fmt::Debug for WebhookMessenger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookMessenger")
+            .field("callback_url", &self.callback_url)
+            .field("status_url", &self.status_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebhookMessenger {
+    /// `callback_url` receives the auth URL via POST, `status_url` is polled
+    /// (via GET) until it reports a redirect URL.
+    pub fn new(callback_url: Url, status_url: Url) -> Self {
+        Self {
+            callback_url,
+            status_url,
+            bearer_token: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            client: reqwest::Client::new(),
+            context: Mutex::new(AuthContext::default()),
+        }
+    }
+
+    /// Authenticate both the callback POST and the status polls with a
+    /// bearer token, so an internet-facing endpoint isn't wide open.
+    pub fn with_bearer_token(mut self, bearer_token: impl Into<String>) -> Self {
+        self.bearer_token = Some(bearer_token.into());
+        self
+    }
+
+    /// Override how often `status_url` is polled.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Override how long to keep polling `status_url` before giving up.
+    pub fn with_poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+
+    /// How long `receive_auth_message` may block waiting for a redirect.
+    /// Callers chaining this messenger into a `CompoundMessenger` should pass
+    /// at least this value to `with_attempt_timeout`.
+    pub fn poll_timeout(&self) -> Duration {
+        self.poll_timeout
+    }
+
+    fn authed(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.bearer_token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    async fn error_for_response(response: reqwest::Response) -> Error {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "<no body>".to_string());
+        Error::ChannelMessenger(format!("webhook callback returned {status}: {body}"))
+    }
+}
+
+#[async_trait]
+impl ChannelMessenger for WebhookMessenger {
+    async fn with_context(&mut self, context: AuthContext) -> Result<(), Error> {
+        *self.context.lock().unwrap() = context;
+        Ok(())
+    }
+
+    async fn send_auth_message(&self) -> Result<(), Error> {
+        let context = self.context.lock().unwrap().clone();
+        let auth_url = context
+            .auth_url
+            .ok_or_else(|| Error::ChannelMessenger("no auth_url in context".to_string()))?;
+
+        let payload = SendAuthPayload {
+            auth_url: auth_url.as_str(),
+            state: context.csrf.as_ref().map(|csrf| csrf.secret().as_str()),
+        };
+
+        let response = self
+            .authed(self.client.post(self.callback_url.clone()).json(&payload))
+            .send()
+            .await
+            .map_err(|e| Error::ChannelMessenger(format!("failed to call webhook: {e}")))?;
+
+        if !response.status().is_success() {
+            return Err(Self::error_for_response(response).await);
+        }
+
+        Ok(())
+    }
+
+    async fn receive_auth_message(&self) -> Result<String, Error> {
+        let context = self.context.lock().unwrap().clone();
+
+        let deadline = tokio::time::Instant::now() + self.poll_timeout;
+        loop {
+            let response = self
+                .authed(self.client.get(self.status_url.clone()))
+                .send()
+                .await
+                .map_err(|e| Error::ChannelMessenger(format!("failed to poll webhook: {e}")))?;
+
+            if !response.status().is_success() {
+                return Err(Self::error_for_response(response).await);
+            }
+
+            let poll: PollResponse = response
+                .json()
+                .await
+                .map_err(|e| Error::ChannelMessenger(format!("invalid webhook response: {e}")))?;
+
+            if let Some(redirect_url) = poll.redirect_url {
+                let redirect_url: Url = redirect_url
+                    .parse()
+                    .map_err(|e| Error::ChannelMessenger(format!("invalid redirect url: {e}")))?;
+
+                return self.extract_code(&context, &redirect_url);
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                return Err(Error::ChannelMessenger(
+                    "timed out waiting for webhook redirect".to_string(),
+                ));
+            }
+
+            sleep(self.poll_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    use oauth2::CsrfToken;
+
+    fn read_request(stream: &mut TcpStream) -> String {
+        let mut buf = [0u8; 4096];
+        let n = stream.read(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    }
+
+    fn respond_json(mut stream: TcpStream, body: &str) {
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_auth_message_posts_to_callback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_request(&mut stream);
+            assert!(request.starts_with("POST"));
+            assert!(request.contains("https://auth.example/authorize"));
+            respond_json(stream, "{}");
+        });
+
+        let callback_url: Url = format!("http://{addr}/callback").parse().unwrap();
+        let status_url: Url = format!("http://{addr}/status").parse().unwrap();
+
+        let mut messenger = WebhookMessenger::new(callback_url, status_url);
+        messenger
+            .with_context(AuthContext {
+                auth_url: Some("https://auth.example/authorize".parse().unwrap()),
+                csrf: Some(CsrfToken::new("CSRF".to_string())),
+                redirect_url: None,
+            })
+            .await
+            .unwrap();
+
+        messenger.send_auth_message().await.unwrap();
+        server.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_receive_polls_until_redirect_appears() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            for i in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = read_request(&mut stream);
+                let body = if i == 0 {
+                    "{\"redirect_url\":null}".to_string()
+                } else {
+                    "{\"redirect_url\":\"https://127.0.0.1:8081/?state=CSRF&code=code\"}"
+                        .to_string()
+                };
+                respond_json(stream, &body);
+            }
+        });
+
+        let callback_url: Url = format!("http://{addr}/callback").parse().unwrap();
+        let status_url: Url = format!("http://{addr}/status").parse().unwrap();
+
+        let mut messenger =
+            WebhookMessenger::new(callback_url, status_url).with_poll_interval(Duration::from_millis(10));
+        messenger
+            .with_context(AuthContext {
+                auth_url: None,
+                csrf: Some(CsrfToken::new("CSRF".to_string())),
+                redirect_url: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!("code", messenger.receive_auth_message().await.unwrap());
+        server.join().unwrap();
+    }
+}